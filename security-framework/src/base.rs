@@ -1,7 +1,9 @@
 //! Support types for other modules.
 
+use core_foundation::dictionary::CFDictionary;
 use core_foundation::string::CFString;
 use core_foundation_sys::base::OSStatus;
+use security_framework_sys::base::{errSecItemNotFound, errSecUserCanceled};
 use std::error;
 use std::fmt;
 use std::result;
@@ -99,18 +101,28 @@ enum ErrorNewImpl {
     OSStatus(OSStatus),
 }
 
+/// A Security Framework error, potentially backed by a `CFError`.
+///
+/// Functions that call into APIs with a `CFErrorRef` out-parameter (signing,
+/// key generation, trust evaluation) return this instead of [`Error`], since
+/// the raw `OSStatus` code alone often loses the real reason a keychain
+/// operation failed. It bridges to and from [`Error`] via `From`, so existing
+/// `OSStatus`-only call sites keep compiling unchanged.
 #[derive(Debug)]
 pub struct ErrorNew(ErrorNewImpl);
 
 impl ErrorNew {
+    /// Creates a new `ErrorNew` from a `CFError`.
     pub fn from_cf_error(cf_error: CFError) -> ErrorNew {
         ErrorNew(ErrorNewImpl::CFError(cf_error))
     }
 
+    /// Creates a new `ErrorNew` from a status code.
     pub fn from_os_status(os_status: OSStatus) -> ErrorNew {
         ErrorNew(ErrorNewImpl::OSStatus(os_status))
     }
 
+    /// Returns the code of the current error.
     pub fn code(&self) -> OSStatus {
         match &self.0 {
             ErrorNewImpl::CFError(cf_error) => cf_error.code() as OSStatus,
@@ -118,16 +130,94 @@ impl ErrorNew {
         }
     }
 
-    pub fn description(&self) -> String {
+    /// Returns the localized description of the current error.
+    pub fn localized_description(&self) -> String {
         match &self.0 {
             ErrorNewImpl::CFError(cf_error) => cf_error.description().to_string(),
             ErrorNewImpl::OSStatus(os_error) => Error::from_code(*os_error).message().unwrap_or_else(|| format!("{}", os_error))
         }
     }
+
+    /// Returns the domain of the current error, e.g. `NSOSStatusErrorDomain`.
+    pub fn domain(&self) -> String {
+        match &self.0 {
+            ErrorNewImpl::CFError(cf_error) => cf_error.domain().to_string(),
+            ErrorNewImpl::OSStatus(_) => "NSOSStatusErrorDomain".to_string(),
+        }
+    }
+
+    /// Returns the `CFError`'s user-info dictionary, if this error is backed
+    /// by one.
+    pub fn user_info(&self) -> Option<CFDictionary> {
+        match &self.0 {
+            ErrorNewImpl::CFError(cf_error) => unsafe {
+                use core_foundation::base::TCFType;
+                use core_foundation_sys::error::CFErrorCopyUserInfo;
+
+                let info = CFErrorCopyUserInfo(cf_error.as_concrete_TypeRef());
+                if info.is_null() {
+                    None
+                } else {
+                    Some(CFDictionary::wrap_under_create_rule(info))
+                }
+            },
+            ErrorNewImpl::OSStatus(_) => None,
+        }
+    }
+
+    /// Returns `true` if this error represents the user canceling an
+    /// operation, e.g. declining a keychain-access prompt.
+    pub fn is_user_canceled(&self) -> bool {
+        self.code() == errSecUserCanceled
+    }
+
+    /// Returns `true` if this error represents a missing keychain item.
+    pub fn is_item_not_found(&self) -> bool {
+        self.code() == errSecItemNotFound
+    }
 }
 
 impl From<Error> for ErrorNew {
     fn from(error: Error) -> ErrorNew {
         ErrorNew::from_os_status(error.code())
     }
-}
\ No newline at end of file
+}
+
+impl fmt::Display for ErrorNew {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}", self.localized_description())
+    }
+}
+
+impl error::Error for ErrorNew {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn error_new_from_os_status_reports_domain() {
+        let error = ErrorNew::from_os_status(errSecUserCanceled);
+        assert_eq!(error.domain(), "NSOSStatusErrorDomain");
+    }
+
+    #[test]
+    fn error_new_is_user_canceled() {
+        let error = ErrorNew::from_os_status(errSecUserCanceled);
+        assert!(error.is_user_canceled());
+        assert!(!error.is_item_not_found());
+    }
+
+    #[test]
+    fn error_new_is_item_not_found() {
+        let error = ErrorNew::from_os_status(errSecItemNotFound);
+        assert!(error.is_item_not_found());
+        assert!(!error.is_user_canceled());
+    }
+
+    #[test]
+    fn error_new_from_os_status_roundtrips_code() {
+        let error = ErrorNew::from_os_status(errSecItemNotFound);
+        assert_eq!(error.code(), errSecItemNotFound);
+    }
+}
@@ -0,0 +1,178 @@
+//! Code signing support.
+//!
+//! Wraps `SecCode`, `SecStaticCode` and `SecRequirement` so callers can
+//! validate the signature and identity of a running process or an on-disk
+//! bundle without dropping down to `security-framework-sys`.
+
+use bitflags::bitflags;
+use core_foundation::base::TCFType;
+use core_foundation::dictionary::CFDictionary;
+use core_foundation::number::CFNumber;
+use core_foundation::string::CFString;
+use core_foundation::url::CFURL;
+use security_framework_sys::base::{errSecParam, SecCodeRef, SecRequirementRef, SecStaticCodeRef};
+use security_framework_sys::code_signing::*;
+use std::path::Path;
+use std::ptr;
+
+use crate::base::{Error, ErrorNew, Result, ResultNew};
+use crate::cvt;
+
+bitflags! {
+    /// Flags controlling code-signing validity checks.
+    ///
+    /// Mirrors the `kSecCS*` options accepted by `SecStaticCodeCheckValidity`
+    /// and `SecCodeCheckValidity`.
+    #[derive(Default)]
+    pub struct SecCsFlags: u32 {
+        /// No special options.
+        const DEFAULT = kSecCSDefaultFlags;
+        /// Validate every architecture contained in a universal binary,
+        /// rather than only the one that would run on the current machine.
+        const CHECK_ALL_ARCHITECTURES = kSecCSCheckAllArchitectures;
+        /// Perform the stricter, slower validation appropriate for
+        /// installers and other security-sensitive consumers.
+        const STRICT_VALIDATE = kSecCSStrictValidate;
+        /// Check whether the signing certificate has been revoked.
+        const ENFORCE_REVOCATION_CHECKS = kSecCSEnforceRevocationChecks;
+        /// Do not consult the network, e.g. for revocation checking.
+        const NO_NETWORK_ACCESS = kSecCSNoNetworkAccess;
+    }
+}
+
+declare_TCFType! {
+    /// A parsed code requirement, used to check whether a code object
+    /// matches an expected signing identity.
+    SecRequirement, SecRequirementRef
+}
+impl_TCFType!(SecRequirement, SecRequirementRef, SecRequirementGetTypeID);
+
+impl SecRequirement {
+    /// Parses a code requirement from its textual representation, e.g.
+    /// `"anchor apple and identifier \"com.example\""`.
+    pub fn from_string(text: &str) -> Result<Self> {
+        unsafe {
+            let text = CFString::new(text);
+            let mut requirement = ptr::null();
+            cvt(SecRequirementCreateWithString(
+                text.as_concrete_TypeRef(),
+                kSecCSDefaultFlags,
+                &mut requirement,
+            ))?;
+            Ok(Self::wrap_under_create_rule(requirement))
+        }
+    }
+}
+
+declare_TCFType! {
+    /// A reference to a dynamic (running) code object, such as a process.
+    SecCode, SecCodeRef
+}
+impl_TCFType!(SecCode, SecCodeRef, SecCodeGetTypeID);
+
+impl SecCode {
+    /// Returns the code object for the calling process.
+    pub fn for_self() -> Result<Self> {
+        unsafe {
+            let mut code = ptr::null();
+            cvt(SecCodeCopySelf(kSecCSDefaultFlags, &mut code))?;
+            Ok(Self::wrap_under_create_rule(code))
+        }
+    }
+
+    /// Returns the code object for the running process with the given pid.
+    pub fn for_guest(pid: i32) -> Result<Self> {
+        unsafe {
+            let pid = CFNumber::from(pid);
+            let attributes = CFDictionary::from_CFType_pairs(&[(
+                CFString::wrap_under_get_rule(kSecGuestAttributePid).as_CFType(),
+                pid.as_CFType(),
+            )]);
+            let mut guest = ptr::null();
+            cvt(SecCodeCopyGuestWithAttributes(
+                ptr::null(),
+                attributes.as_concrete_TypeRef(),
+                kSecCSDefaultFlags,
+                &mut guest,
+            ))?;
+            Ok(Self::wrap_under_create_rule(guest))
+        }
+    }
+
+    /// Checks whether this code object satisfies `requirement`.
+    ///
+    /// On failure the `CFError` carrying the actual requirement-mismatch
+    /// reason (`kSecCSErrorReason`) is surfaced via [`ErrorNew`], since the
+    /// plain `OSStatus` alone loses that detail.
+    pub fn check_validity(&self, flags: SecCsFlags, requirement: &SecRequirement) -> ResultNew<()> {
+        unsafe {
+            let mut error = ptr::null_mut();
+            let status = SecCodeCheckValidityWithErrors(
+                self.as_concrete_TypeRef(),
+                flags.bits(),
+                requirement.as_concrete_TypeRef(),
+                &mut error,
+            );
+            check_validity_result(status, error)
+        }
+    }
+}
+
+declare_TCFType! {
+    /// A reference to an on-disk code object, such as an app bundle or
+    /// executable.
+    SecStaticCode, SecStaticCodeRef
+}
+impl_TCFType!(SecStaticCode, SecStaticCodeRef, SecStaticCodeGetTypeID);
+
+impl SecStaticCode {
+    /// Creates a static code object for the bundle or executable at `path`.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        unsafe {
+            let url = CFURL::from_path(path, false).ok_or_else(|| Error::from_code(errSecParam))?;
+            let mut code = ptr::null();
+            cvt(SecStaticCodeCreateWithPath(
+                url.as_concrete_TypeRef(),
+                kSecCSDefaultFlags,
+                &mut code,
+            ))?;
+            Ok(Self::wrap_under_create_rule(code))
+        }
+    }
+
+    /// Checks whether this code object satisfies `requirement`.
+    ///
+    /// On failure the `CFError` carrying the actual requirement-mismatch
+    /// reason (`kSecCSErrorReason`) is surfaced via [`ErrorNew`], since the
+    /// plain `OSStatus` alone loses that detail.
+    pub fn check_validity(&self, flags: SecCsFlags, requirement: &SecRequirement) -> ResultNew<()> {
+        unsafe {
+            let mut error = ptr::null_mut();
+            let status = SecStaticCodeCheckValidityWithErrors(
+                self.as_concrete_TypeRef(),
+                flags.bits(),
+                requirement.as_concrete_TypeRef(),
+                &mut error,
+            );
+            check_validity_result(status, error)
+        }
+    }
+}
+
+/// Turns the `(OSStatus, CFErrorRef)` pair returned by the
+/// `…CheckValidityWithErrors` functions into a [`ResultNew`], preferring the
+/// richer `CFError` when the call produced one.
+unsafe fn check_validity_result(
+    status: security_framework_sys::base::OSStatus,
+    error: core_foundation_sys::error::CFErrorRef,
+) -> ResultNew<()> {
+    if status == security_framework_sys::base::errSecSuccess {
+        return Ok(());
+    }
+    if !error.is_null() {
+        return Err(ErrorNew::from_cf_error(
+            core_foundation::error::CFError::wrap_under_create_rule(error),
+        ));
+    }
+    Err(ErrorNew::from_os_status(status))
+}
@@ -1,17 +1,304 @@
 //! Identity support.
 
+use core_foundation::array::CFArray;
 use core_foundation::base::TCFType;
-use security_framework_sys::base::SecIdentityRef;
+use core_foundation::boolean::CFBoolean;
+use core_foundation::data::CFData;
+use core_foundation::dictionary::{CFDictionary, CFMutableDictionary};
+use core_foundation::number::CFNumber;
+use core_foundation::string::CFString;
+use core_foundation_sys::base::CFTypeRef;
+use security_framework_sys::base::{errSecItemNotFound, SecIdentityRef};
+use security_framework_sys::certificate::{
+    kSecPropertyKeyValue, SecCertificateCopyValues,
+};
 use security_framework_sys::identity::*;
+use security_framework_sys::item::{
+    kSecAttrIssuer, kSecAttrSerialNumber, kSecClass, kSecClassIdentity, kSecMatchLimit,
+    kSecMatchLimitAll, kSecReturnAttributes, kSecReturnRef, kSecValueRef, SecItemCopyMatching,
+    SecItemDelete,
+};
+use security_framework_sys::key::{
+    kSecAttrKeySizeInBits, kSecAttrKeyType, kSecAttrKeyTypeEC, kSecAttrKeyTypeRSA,
+    kSecKeyAlgorithmECDSASignatureDigestX962SHA256,
+    kSecKeyAlgorithmECDSASignatureDigestX962SHA384,
+    kSecKeyAlgorithmECDSASignatureDigestX962SHA512,
+    kSecKeyAlgorithmRSASignatureDigestPKCS1v15SHA256,
+    kSecKeyAlgorithmRSASignatureDigestPKCS1v15SHA384,
+    kSecKeyAlgorithmRSASignatureDigestPKCS1v15SHA512, SecKeyCopyAttributes,
+    SecKeyCreateSignature,
+};
 use std::fmt;
 use std::ptr;
+use std::result;
 
-use crate::base::Result;
+use crate::base::{Error, ErrorNew, Result, ResultNew};
 use crate::certificate::SecCertificate;
 use crate::cvt;
 use crate::import_export::Pkcs12ImportOptions;
 use crate::key::SecKey;
 
+/// OID of the extended key usage certificate extension (RFC 5280 §4.2.1.12).
+const OID_EXTENDED_KEY_USAGE: &str = "2.5.29.37";
+/// OID of the `id-kp-clientAuth` extended key usage.
+const OID_EKU_CLIENT_AUTH: &str = "1.3.6.1.5.5.7.3.2";
+/// OID of the key usage certificate extension (RFC 5280 §4.2.1.3).
+const OID_KEY_USAGE: &str = "2.5.29.15";
+/// The `digitalSignature` bit of the `SecKeyUsage` bitmask that
+/// `SecCertificateCopyValues` reports for the key-usage extension. This is
+/// `kSecKeyUsageDigitalSignature` from `Security/SecCertificate.h`, which
+/// numbers the BIT STRING's bits LSB-first (`digitalSignature` is bit 0),
+/// not the X.509 wire encoding's MSB-first first byte.
+const KEY_USAGE_DIGITAL_SIGNATURE_BIT: u32 = 0x01;
+
+/// A digest algorithm that can be signed with [`SecIdentity::sign_digest`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DigestAlgorithm {
+    /// SHA-256
+    Sha256,
+    /// SHA-384
+    Sha384,
+    /// SHA-512
+    Sha512,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum KeyKind {
+    Rsa,
+    Ec,
+}
+
+/// An error returned by [`SecIdentity::delete`].
+#[derive(Debug)]
+pub enum DeleteIdentityError {
+    /// No matching identity was found in the keychain, so there was nothing
+    /// to delete. Callers that just want cleanup to be idempotent can treat
+    /// this the same as success.
+    NotFound,
+    /// Some other failure occurred while deleting the identity.
+    Other(Error),
+}
+
+impl fmt::Display for DeleteIdentityError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound => write!(fmt, "identity not found in keychain"),
+            Self::Other(error) => fmt::Display::fmt(error, fmt),
+        }
+    }
+}
+
+impl std::error::Error for DeleteIdentityError {}
+
+/// An identity returned by [`SecIdentity::find`], along with the certificate
+/// metadata requested via [`SecIdentitySearchOptions`].
+///
+/// `issuer` and `serial` are the raw DER encodings of the certificate's issuer
+/// name and serial number, suitable for matching against a server's
+/// accepted-CA list without building out the whole certificate chain.
+#[derive(Debug)]
+pub struct SecIdentityMatch {
+    /// The matched identity.
+    pub identity: SecIdentity,
+    /// The DER-encoded issuer name of the identity's certificate.
+    pub issuer: Option<Vec<u8>>,
+    /// The DER-encoded serial number of the identity's certificate.
+    pub serial: Option<Vec<u8>>,
+}
+
+/// Options for [`SecIdentity::find`].
+#[derive(Debug, Default)]
+pub struct SecIdentitySearchOptions {
+    valid_client_auth_only: bool,
+    include_issuer_and_serial: bool,
+}
+
+impl SecIdentitySearchOptions {
+    /// Creates a new, empty set of options.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restricts the search to identities whose certificate advertises the
+    /// TLS client-auth EKU or digital-signature key usage, and whose private
+    /// key is actually present and signable.
+    #[inline(always)]
+    pub fn valid_client_auth_only(&mut self, value: bool) -> &mut Self {
+        self.valid_client_auth_only = value;
+        self
+    }
+
+    /// Requests that the DER-encoded issuer and serial number of each match's
+    /// certificate be returned alongside the identity.
+    #[inline(always)]
+    pub fn include_issuer_and_serial(&mut self, value: bool) -> &mut Self {
+        self.include_issuer_and_serial = value;
+        self
+    }
+
+    /// Searches the user's keychains for identities matching these options.
+    pub fn search(&self) -> Result<Vec<SecIdentityMatch>> {
+        unsafe {
+            let mut query = CFMutableDictionary::new();
+            query.add(
+                &kSecClass as *const _ as *const _,
+                &kSecClassIdentity as *const _ as *const _,
+            );
+            query.add(
+                &kSecMatchLimit as *const _ as *const _,
+                &kSecMatchLimitAll as *const _ as *const _,
+            );
+            query.add(
+                &kSecReturnRef as *const _ as *const _,
+                CFBoolean::true_value().as_CFTypeRef() as *const _,
+            );
+            // Always ask for attributes too: with only `kSecReturnRef` set,
+            // `SecItemCopyMatching` returns a `CFArray` of bare
+            // `SecIdentityRef`s rather than dictionaries, which would need a
+            // different unwrapping path than the one below.
+            query.add(
+                &kSecReturnAttributes as *const _ as *const _,
+                CFBoolean::true_value().as_CFTypeRef() as *const _,
+            );
+
+            let mut result = ptr::null();
+            let status = SecItemCopyMatching(query.to_immutable().as_concrete_TypeRef(), &mut result);
+            if status == errSecItemNotFound {
+                return Ok(Vec::new());
+            }
+            cvt(status)?;
+
+            let items: CFArray<CFDictionary> = CFArray::wrap_under_create_rule(result as _);
+            let mut matches = Vec::with_capacity(items.len() as usize);
+            for item in items.iter() {
+                let Some(identity_ref) = item
+                    .find(kSecValueRef as *const _ as CFTypeRef)
+                    .map(|v| *v as SecIdentityRef)
+                else {
+                    continue;
+                };
+                let identity = SecIdentity::wrap_under_get_rule(identity_ref);
+
+                if self.valid_client_auth_only && !is_valid_for_client_auth(&identity) {
+                    continue;
+                }
+
+                let (issuer, serial) = if self.include_issuer_and_serial {
+                    let issuer = item
+                        .find(kSecAttrIssuer as *const _ as CFTypeRef)
+                        .map(|v| CFData::wrap_under_get_rule(*v as _).to_vec());
+                    let serial = item
+                        .find(kSecAttrSerialNumber as *const _ as CFTypeRef)
+                        .map(|v| CFData::wrap_under_get_rule(*v as _).to_vec());
+                    (issuer, serial)
+                } else {
+                    (None, None)
+                };
+
+                matches.push(SecIdentityMatch {
+                    identity,
+                    issuer,
+                    serial,
+                });
+            }
+            Ok(matches)
+        }
+    }
+}
+
+/// Checks whether an identity's certificate advertises the TLS client-auth
+/// EKU or the `digitalSignature` key usage, and its private key is actually
+/// present and signable.
+///
+/// This deliberately does not run a trust evaluation: a client certificate
+/// signed by an enterprise or self-signed CA that isn't in the system trust
+/// store would fail evaluation even though it's perfectly usable for client
+/// auth, so trust is not a usability proxy here.
+fn is_valid_for_client_auth(identity: &SecIdentity) -> bool {
+    let certificate = match identity.certificate() {
+        Ok(certificate) => certificate,
+        Err(_) => return false,
+    };
+    // `supported_algorithms` fails when the private key is missing or is
+    // neither RSA nor EC, and is empty when an RSA modulus is too small for
+    // any digest `sign_digest` accepts.
+    match identity.supported_algorithms() {
+        Ok(algorithms) if !algorithms.is_empty() => {}
+        _ => return false,
+    }
+
+    certificate_allows_client_auth(&certificate)
+}
+
+/// Reads the extended-key-usage and key-usage extensions off `certificate`
+/// via `SecCertificateCopyValues` and checks for `id-kp-clientAuth` or the
+/// `digitalSignature` key usage bit.
+fn certificate_allows_client_auth(certificate: &SecCertificate) -> bool {
+    unsafe {
+        let keys = CFArray::from_CFTypes(&[
+            CFString::new(OID_EXTENDED_KEY_USAGE),
+            CFString::new(OID_KEY_USAGE),
+        ]);
+        let mut error = ptr::null_mut();
+        let values = SecCertificateCopyValues(
+            certificate.as_concrete_TypeRef(),
+            keys.as_concrete_TypeRef(),
+            &mut error,
+        );
+        if values.is_null() {
+            if !error.is_null() {
+                drop(core_foundation::error::CFError::wrap_under_create_rule(error));
+            }
+            return false;
+        }
+        let values: CFDictionary = CFDictionary::wrap_under_create_rule(values);
+
+        let eku = property_value(&values, OID_EXTENDED_KEY_USAGE);
+        let has_client_auth_eku = eku
+            .map(|value| extension_oids_contain(value, OID_EKU_CLIENT_AUTH))
+            .unwrap_or(false);
+
+        let ku = property_value(&values, OID_KEY_USAGE);
+        let has_digital_signature_ku = ku
+            .map(|value| key_usage_has_bit(value, KEY_USAGE_DIGITAL_SIGNATURE_BIT))
+            .unwrap_or(false);
+
+        has_client_auth_eku || has_digital_signature_ku
+    }
+}
+
+/// Extracts the `kSecPropertyKeyValue` entry of the property dictionary
+/// returned by `SecCertificateCopyValues` for `oid`.
+unsafe fn property_value(values: &CFDictionary, oid: &str) -> Option<CFTypeRef> {
+    let property = values.find(CFString::new(oid).as_CFTypeRef())?;
+    let property: CFDictionary = CFDictionary::wrap_under_get_rule(*property as _);
+    property
+        .find(kSecPropertyKeyValue as *const _ as CFTypeRef)
+        .map(|value| *value)
+}
+
+/// Returns `true` if an extended-key-usage property's value, a `CFArray` of
+/// per-purpose property dictionaries (each `{kSecPropertyKeyType,
+/// kSecPropertyKeyLabel, kSecPropertyKeyValue}`), contains a purpose whose
+/// own `kSecPropertyKeyValue` is the dotted-decimal OID string `oid`.
+unsafe fn extension_oids_contain(value: CFTypeRef, oid: &str) -> bool {
+    let purposes: CFArray<CFDictionary> = CFArray::wrap_under_get_rule(value as _);
+    purposes.iter().any(|purpose| {
+        purpose
+            .find(kSecPropertyKeyValue as *const _ as CFTypeRef)
+            .map(|purpose_value| CFString::wrap_under_get_rule(*purpose_value as _).to_string() == oid)
+            .unwrap_or(false)
+    })
+}
+
+/// Returns `true` if a key-usage property's value, the usage `BIT STRING`
+/// decoded as an integer, has `bit` set.
+unsafe fn key_usage_has_bit(value: CFTypeRef, bit: u32) -> bool {
+    let number: CFNumber = CFNumber::wrap_under_get_rule(value as _);
+    number.to_i64().map(|usage| usage as u32 & bit != 0).unwrap_or(false)
+}
+
 declare_TCFType! {
     /// A type representing an identity.
     ///
@@ -52,6 +339,16 @@ impl SecIdentity {
             .collect())
     }
 
+    /// Returns every identity in the user's keychains.
+    pub fn find_all() -> Result<Vec<SecIdentityMatch>> {
+        SecIdentitySearchOptions::new().search()
+    }
+
+    /// Searches the user's keychains for identities matching `options`.
+    pub fn find(options: &SecIdentitySearchOptions) -> Result<Vec<SecIdentityMatch>> {
+        options.search()
+    }
+
     /// Returns the certificate corresponding to this identity.
     pub fn certificate(&self) -> Result<SecCertificate> {
         unsafe {
@@ -69,11 +366,162 @@ impl SecIdentity {
             Ok(SecKey::wrap_under_create_rule(key))
         }
     }
+
+    /// Deletes this identity, and thereby its private key and certificate
+    /// association, from the keychain.
+    ///
+    /// A missing identity is reported as [`DeleteIdentityError::NotFound`]
+    /// rather than a generic error, so cleanup code can treat it the same as
+    /// success and remain idempotent.
+    ///
+    /// On macOS this may prompt the user for keychain access depending on
+    /// the item's ACL.
+    pub fn delete(&self) -> result::Result<(), DeleteIdentityError> {
+        unsafe {
+            let mut query = CFMutableDictionary::new();
+            query.add(
+                &kSecValueRef as *const _ as *const _,
+                self.as_concrete_TypeRef() as *const _,
+            );
+            let status = SecItemDelete(query.to_immutable().as_concrete_TypeRef());
+            if status == errSecItemNotFound {
+                return Err(DeleteIdentityError::NotFound);
+            }
+            cvt(status).map_err(DeleteIdentityError::Other)
+        }
+    }
+
+    /// Returns the digest algorithms this identity's private key can sign with.
+    ///
+    /// EC keys can sign any of SHA-256/384/512 regardless of their field
+    /// size. RSA keys are limited by their modulus size: PKCS#1 v1.5 needs
+    /// room for the 11-byte padding, the digest's `DigestInfo` prefix, and
+    /// the digest itself, so a small RSA key (e.g. 512 bits) cannot sign a
+    /// SHA-512 digest.
+    pub fn supported_algorithms(&self) -> Result<Vec<DigestAlgorithm>> {
+        let key = self.private_key()?;
+        let (kind, key_size_in_bits) = Self::key_kind_and_size(&key)?;
+        Ok([
+            DigestAlgorithm::Sha256,
+            DigestAlgorithm::Sha384,
+            DigestAlgorithm::Sha512,
+        ]
+        .into_iter()
+        .filter(|digest| match kind {
+            KeyKind::Rsa => key_size_in_bits >= rsa_min_modulus_bits(*digest),
+            KeyKind::Ec => true,
+        })
+        .collect())
+    }
+
+    /// Signs a pre-computed digest with this identity's private key.
+    ///
+    /// The key type (RSA or EC) is read from the private key's attributes and used
+    /// to select the matching `SecKeyAlgorithm`: RSA signatures are produced as
+    /// PKCS#1 v1.5 over the supplied digest (the Security framework, not the
+    /// caller, prepends the `DigestInfo` prefix for the chosen hash), while EC
+    /// signatures are returned as a DER-encoded ASN.1 `SEQUENCE { r, s }` exactly
+    /// as TLS expects them, with no re-packing to fixed-width integers.
+    pub fn sign_digest(&self, digest_algorithm: DigestAlgorithm, data: &[u8]) -> ResultNew<Vec<u8>> {
+        let key = self.private_key().map_err(ErrorNew::from)?;
+        let (kind, _) = Self::key_kind_and_size(&key).map_err(ErrorNew::from)?;
+        let algorithm = match (kind, digest_algorithm) {
+            (KeyKind::Rsa, DigestAlgorithm::Sha256) => unsafe {
+                kSecKeyAlgorithmRSASignatureDigestPKCS1v15SHA256
+            },
+            (KeyKind::Rsa, DigestAlgorithm::Sha384) => unsafe {
+                kSecKeyAlgorithmRSASignatureDigestPKCS1v15SHA384
+            },
+            (KeyKind::Rsa, DigestAlgorithm::Sha512) => unsafe {
+                kSecKeyAlgorithmRSASignatureDigestPKCS1v15SHA512
+            },
+            (KeyKind::Ec, DigestAlgorithm::Sha256) => unsafe {
+                kSecKeyAlgorithmECDSASignatureDigestX962SHA256
+            },
+            (KeyKind::Ec, DigestAlgorithm::Sha384) => unsafe {
+                kSecKeyAlgorithmECDSASignatureDigestX962SHA384
+            },
+            (KeyKind::Ec, DigestAlgorithm::Sha512) => unsafe {
+                kSecKeyAlgorithmECDSASignatureDigestX962SHA512
+            },
+        };
+
+        unsafe {
+            let digest = CFData::from_buffer(data);
+            let mut error = ptr::null_mut();
+            let signature = SecKeyCreateSignature(
+                key.as_concrete_TypeRef(),
+                algorithm,
+                digest.as_concrete_TypeRef(),
+                &mut error,
+            );
+            if signature.is_null() {
+                return Err(ErrorNew::from_cf_error(
+                    core_foundation::error::CFError::wrap_under_create_rule(error),
+                ));
+            }
+            Ok(CFData::wrap_under_create_rule(signature).to_vec())
+        }
+    }
+
+    /// Reads `kSecAttrKeyType` and `kSecAttrKeySizeInBits` off a private
+    /// key's attribute dictionary to tell RSA and EC keys apart and learn
+    /// their modulus/field size.
+    fn key_kind_and_size(key: &SecKey) -> Result<(KeyKind, u32)> {
+        unsafe {
+            let attrs = SecKeyCopyAttributes(key.as_concrete_TypeRef());
+            if attrs.is_null() {
+                return Err(Error::from_code(
+                    security_framework_sys::base::errSecParam,
+                ));
+            }
+            let attrs: CFDictionary = CFDictionary::wrap_under_create_rule(attrs);
+            let key_type = attrs
+                .find(kSecAttrKeyType as *const _ as CFTypeRef)
+                .map(|v| CFString::wrap_under_get_rule(*v as _));
+
+            let kind = match key_type {
+                Some(key_type) if key_type == CFString::wrap_under_get_rule(kSecAttrKeyTypeRSA) => {
+                    KeyKind::Rsa
+                }
+                Some(key_type) if key_type == CFString::wrap_under_get_rule(kSecAttrKeyTypeEC) => {
+                    KeyKind::Ec
+                }
+                _ => {
+                    return Err(Error::from_code(
+                        security_framework_sys::base::errSecParam,
+                    ))
+                }
+            };
+
+            let size_in_bits = attrs
+                .find(kSecAttrKeySizeInBits as *const _ as CFTypeRef)
+                .and_then(|v| CFNumber::wrap_under_get_rule(*v as _).to_i64())
+                .unwrap_or(0) as u32;
+
+            Ok((kind, size_in_bits))
+        }
+    }
+}
+
+/// The minimum RSA modulus size, in bits, able to carry a PKCS#1 v1.5
+/// signature over `digest`: 11 bytes of padding, plus the digest's
+/// `DigestInfo` prefix (19 bytes for all of SHA-256/384/512), plus the
+/// digest itself.
+fn rsa_min_modulus_bits(digest: DigestAlgorithm) -> u32 {
+    const PKCS1_PADDING_OVERHEAD_BYTES: u32 = 11;
+    const DIGEST_INFO_PREFIX_BYTES: u32 = 19;
+    let digest_bytes = match digest {
+        DigestAlgorithm::Sha256 => 32,
+        DigestAlgorithm::Sha384 => 48,
+        DigestAlgorithm::Sha512 => 64,
+    };
+    (PKCS1_PADDING_OVERHEAD_BYTES + DIGEST_INFO_PREFIX_BYTES + digest_bytes) * 8
 }
 
 #[cfg(test)]
 mod test {
-    use super::SecIdentity;
+    use super::*;
 
     #[test]
     fn identity_has_send_bound() {
@@ -87,4 +535,46 @@ mod test {
         let identities = SecIdentity::from_pkcs12(pkcs12_der, "password123").unwrap();
         assert_eq!(1, identities.len());
     }
+
+    #[test]
+    fn rsa_min_modulus_bits_grows_with_digest_size() {
+        let sha256 = rsa_min_modulus_bits(DigestAlgorithm::Sha256);
+        let sha384 = rsa_min_modulus_bits(DigestAlgorithm::Sha384);
+        let sha512 = rsa_min_modulus_bits(DigestAlgorithm::Sha512);
+        assert_eq!(sha256, (11 + 19 + 32) * 8);
+        assert_eq!(sha384, (11 + 19 + 48) * 8);
+        assert_eq!(sha512, (11 + 19 + 64) * 8);
+        assert!(sha256 < sha384);
+        assert!(sha384 < sha512);
+    }
+
+    #[test]
+    fn sec_identity_search_options_defaults_to_unfiltered() {
+        let options = SecIdentitySearchOptions::new();
+        assert!(!options.valid_client_auth_only);
+        assert!(!options.include_issuer_and_serial);
+    }
+
+    #[test]
+    fn sec_identity_search_options_builder_chains() {
+        let mut options = SecIdentitySearchOptions::new();
+        options
+            .valid_client_auth_only(true)
+            .include_issuer_and_serial(true);
+        assert!(options.valid_client_auth_only);
+        assert!(options.include_issuer_and_serial);
+    }
+
+    #[test]
+    fn delete_identity_error_not_found_display() {
+        let error = DeleteIdentityError::NotFound;
+        assert_eq!(error.to_string(), "identity not found in keychain");
+    }
+
+    #[test]
+    fn delete_identity_error_other_display_matches_inner_error() {
+        let inner = crate::base::Error::from_code(security_framework_sys::base::errSecParam);
+        let error = DeleteIdentityError::Other(inner);
+        assert_eq!(error.to_string(), inner.to_string());
+    }
 }